@@ -0,0 +1,172 @@
+use std::io::Write;
+
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+use hyper::body::HttpBody;
+use hyper::header::{HeaderValue, CONTENT_ENCODING};
+use hyper::{Body, Response};
+
+/// Bodies smaller than this aren't worth the CPU cost of compressing.
+const COMPRESSION_THRESHOLD: usize = 860;
+
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+fn preferred_encoding(accept_encoding: Option<&HeaderValue>) -> Option<Encoding> {
+    let accept_encoding = accept_encoding?.to_str().ok()?;
+
+    if accepts(accept_encoding, "gzip") {
+        Some(Encoding::Gzip)
+    } else if accepts(accept_encoding, "deflate") {
+        Some(Encoding::Deflate)
+    } else {
+        None
+    }
+}
+
+/// True if `accept_encoding` names `coding` with a nonzero `q` value (or no
+/// `q` at all, which defaults to 1). Per RFC 7231 §5.3.4, `q=0` means the
+/// client explicitly refuses that coding, so it must not be treated as accepted.
+fn accepts(accept_encoding: &str, coding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut params = item.split(';');
+        let name = params.next().unwrap_or("").trim();
+        if !name.eq_ignore_ascii_case(coding) {
+            return false;
+        }
+
+        let q = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|value| value.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        q > 0.0
+    })
+}
+
+/// Compresses `response`'s body when the client advertised support for it
+/// via `Accept-Encoding` and the body is large enough to be worth it.
+/// Responses that are already encoded, too small, or whose exact size isn't
+/// known up front (streamed/chunked bodies, e.g. a relayed upstream response
+/// sent without a `Content-Length`) are returned unchanged rather than
+/// buffered into memory.
+pub async fn compress_response(
+    accept_encoding: Option<&HeaderValue>,
+    response: Response<Body>,
+) -> Result<Response<Body>, hyper::Error> {
+    if response.headers().contains_key(CONTENT_ENCODING) {
+        return Ok(response);
+    }
+
+    let encoding = match preferred_encoding(accept_encoding) {
+        Some(encoding) => encoding,
+        None => return Ok(response),
+    };
+
+    let (mut parts, body) = response.into_parts();
+
+    match body.size_hint().exact() {
+        Some(size) if size >= COMPRESSION_THRESHOLD as u64 => {}
+        _ => return Ok(Response::from_parts(parts, body)),
+    }
+
+    let bytes = hyper::body::to_bytes(body).await?;
+
+    let compressed = match encoding {
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(&bytes)
+                .expect("writing to an in-memory encoder can't fail");
+            encoder
+                .finish()
+                .expect("finishing an in-memory encoder can't fail")
+        }
+        Encoding::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(&bytes)
+                .expect("writing to an in-memory encoder can't fail");
+            encoder
+                .finish()
+                .expect("finishing an in-memory encoder can't fail")
+        }
+    };
+
+    parts.headers.insert(
+        CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    parts.headers.remove(hyper::header::CONTENT_LENGTH);
+
+    Ok(Response::from_parts(parts, Body::from(compressed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn compresses_in_memory_body_above_threshold() {
+        let body = "x".repeat(COMPRESSION_THRESHOLD + 1);
+        let response = Response::new(Body::from(body));
+        let accept_encoding = HeaderValue::from_static("gzip");
+
+        let compressed = compress_response(Some(&accept_encoding), response)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            compressed.headers().get(CONTENT_ENCODING),
+            Some(&HeaderValue::from_static("gzip"))
+        );
+    }
+
+    #[tokio::test]
+    async fn leaves_body_below_threshold_uncompressed() {
+        let response = Response::new(Body::from("short"));
+        let accept_encoding = HeaderValue::from_static("gzip");
+
+        let response = compress_response(Some(&accept_encoding), response)
+            .await
+            .unwrap();
+
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_deflate_when_gzip_is_refused_via_q0() {
+        let body = "x".repeat(COMPRESSION_THRESHOLD + 1);
+        let response = Response::new(Body::from(body));
+        let accept_encoding = HeaderValue::from_static("gzip;q=0, deflate");
+
+        let response = compress_response(Some(&accept_encoding), response)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(CONTENT_ENCODING),
+            Some(&HeaderValue::from_static("deflate"))
+        );
+    }
+
+    #[test]
+    fn accepts_treats_q0_as_refused_and_missing_q_as_accepted() {
+        assert!(accepts("gzip", "gzip"));
+        assert!(accepts("gzip;q=0.5", "gzip"));
+        assert!(!accepts("gzip;q=0", "gzip"));
+        assert!(!accepts("gzip;q=0.0", "gzip"));
+        assert!(!accepts("deflate", "gzip"));
+    }
+}
@@ -1,183 +1,752 @@
-use std::fmt;
 use std::net::SocketAddr;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
-use futures::future;
-use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use dashmap::DashMap;
+use hyper::header::{ACCEPT, ACCEPT_ENCODING, CONTENT_TYPE};
 use hyper::service::Service;
-use lazy_static::lazy_static;
+use hyper::{body, Body, Method, Request, Response, Server, StatusCode};
 use regex::Regex;
-use slab::Slab;
+use serde::{Deserialize, Serialize};
+
+mod compress;
+mod relay;
+mod router;
+mod templates;
+
+use compress::compress_response;
+use relay::{Relay, RelayConfig};
+use router::{Captures, HandlerFuture, Matched, Router};
+use templates::Templates;
 
 type UserId = u64;
-type UserDb = Arc<Mutex<Slab<UserData>>>;
-
-const INDEX: &str = r#"
-<!doctype html>
-<html>
-    <head>
-        <title>Rust Microservice</title>
-    </head>
-    <body>
-        <h3>Rust Microservice</h3>
-    </body>
-</html>
-"#;
-
-lazy_static! {
-    static ref INDEX_PATH: Regex = Regex::new("^/(index\\.html?)?$").unwrap();
-    static ref USER_PATH: Regex = Regex::new("^/user/((?P<user_id>\\d+?)/?)?$").unwrap();
-    static ref USERS_PATH: Regex = Regex::new("^/users/?$").unwrap();
+type UserDb = Arc<UserStore>;
+
+/// A concurrent user store: reads and writes to different users proceed
+/// without taking a global lock, so no guard is ever held across an await
+/// point once handlers read request bodies asynchronously.
+#[derive(Debug, Default)]
+struct UserStore {
+    users: DashMap<UserId, UserData>,
+    next_id: AtomicU64,
 }
 
-#[derive(Debug)]
-struct UserData;
+impl UserStore {
+    fn insert(&self, data: UserData) -> UserId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.users.insert(id, data);
+        id
+    }
+}
+
+/// Runtime configuration for the service, in place of hard-coded constants.
+#[derive(Debug, Clone)]
+struct Config {
+    bind_addr: SocketAddr,
+    request_timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            request_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Config {
+    /// Starts from `Config::default()` and overrides `bind_addr`/
+    /// `request_timeout` from `BIND_ADDR`/`REQUEST_TIMEOUT_SECS` when set,
+    /// the same env-var override pattern `build_relay_config` uses for
+    /// `RELAY_ROUTES`. Malformed values are logged and the default is kept
+    /// rather than failing startup.
+    fn from_env() -> Self {
+        let mut config = Config::default();
+
+        if let Ok(bind_addr) = std::env::var("BIND_ADDR") {
+            match bind_addr.parse() {
+                Ok(addr) => config.bind_addr = addr,
+                Err(e) => eprintln!("BIND_ADDR: invalid address {:?}: {}", bind_addr, e),
+            }
+        }
+
+        if let Ok(timeout_secs) = std::env::var("REQUEST_TIMEOUT_SECS") {
+            match timeout_secs.parse() {
+                Ok(secs) => config.request_timeout = Duration::from_secs(secs),
+                Err(e) => eprintln!(
+                    "REQUEST_TIMEOUT_SECS: invalid value {:?}: {}",
+                    timeout_secs, e
+                ),
+            }
+        }
+
+        config
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct UserData {
+    name: String,
+    email: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    error: String,
+}
 
 #[derive(Debug)]
 pub struct MicroService {
-    user_db: UserDb
+    router: Arc<Router>,
+    relay: Relay,
+    user_db: UserDb,
+    templates: Templates,
+    request_timeout: Duration,
 }
 
 pub struct MakeMicroService {
-    user_db: UserDb
+    router: Arc<Router>,
+    relay: Relay,
+    user_db: UserDb,
+    templates: Templates,
+    request_timeout: Duration,
 }
 
 impl MicroService {
-    fn new(user_db: UserDb) -> Self {
+    fn new(
+        router: Arc<Router>,
+        relay: Relay,
+        user_db: UserDb,
+        templates: Templates,
+        request_timeout: Duration,
+    ) -> Self {
         MicroService {
-            user_db
+            router,
+            relay,
+            user_db,
+            templates,
+            request_timeout,
         }
     }
 }
 
 impl MakeMicroService {
-    fn new(user_db: UserDb) -> Self {
+    fn new(
+        router: Arc<Router>,
+        relay: Relay,
+        user_db: UserDb,
+        templates: Templates,
+        request_timeout: Duration,
+    ) -> Self {
         MakeMicroService {
-            user_db
+            router,
+            relay,
+            user_db,
+            templates,
+            request_timeout,
         }
     }
 }
 
-impl fmt::Display for UserData {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("{}")
-    }
-}
-
 impl Service<Request<Body>> for MicroService {
     type Response = Response<Body>;
     type Error = hyper::Error;
-    type Future = future::Ready<Result<Self::Response, Self::Error>>;
+    type Future = HandlerFuture;
 
-        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-            Ok(()).into()
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
     }
 
     fn call(&mut self, req: Request<Body>) -> Self::Future {
-        let response = {
-            let method = req.method();
-            let path = req.uri().path();
-            let mut users = self.user_db.lock().unwrap();
-
-            if INDEX_PATH.is_match(path) {
-                if method == &Method::GET {
-                    Response::new(INDEX.into())
-                } else {
-                    response_with_code(StatusCode::METHOD_NOT_ALLOWED)
-                }
-            } else if USERS_PATH.is_match(path) {
-                if method == &Method::GET {
-                    let list = users.iter()
-                                            .map(|(id, _)| id.to_string())
-                                            .collect::<Vec<String>>()
-                                            .join(",");
-
-                    Response::new(list.into())
-                } else {
-                    response_with_code(StatusCode::METHOD_NOT_ALLOWED)
+        let path = req.uri().path().to_owned();
+        let accept_encoding = req.headers().get(ACCEPT_ENCODING).cloned();
+
+        let fut: HandlerFuture = if let Some(upstream) = self.relay.find(&path) {
+            let relay = self.relay.clone();
+            Box::pin(async move { Ok(relay.forward(upstream, req).await) })
+        } else {
+            let user_db = self.user_db.clone();
+            let templates = self.templates.clone();
+
+            match self.router.find(&path, req.method()) {
+                Matched::Found { handler, captures } => handler(req, captures, user_db, templates),
+                Matched::MethodNotAllowed => {
+                    Box::pin(async { Ok(response_with_code(StatusCode::METHOD_NOT_ALLOWED)) })
                 }
-            } else if let Some(cap) = USER_PATH.captures(path) {
-                let user_id = cap.name("user_id").and_then(|m| {
-                    m.as_str()
-                        .parse::<UserId>()
-                        .ok()
-                        .map(|x| x as usize)
-                });
-
-                match (method, user_id) {
-                    (&Method::POST, None) => {
-                        let id = users.insert(UserData);
-                        Response::new(id.to_string().into())
-                    },
-                    (&Method::POST, Some(_)) => {
-                        response_with_code(StatusCode::BAD_REQUEST)
-                    },
-                    (&Method::GET, Some(id)) => {
-                        if let Some(data) = users.get(id) {
-                            Response::new(data.to_string().into())
-                        } else {
-                            response_with_code(StatusCode::NOT_FOUND)
-                        }
-                    },
-                    (&Method::PUT, Some(id)) => {
-                        if let Some(data) = users.get_mut(id){
-                            *data = UserData;
-                            response_with_code(StatusCode::OK)
-                        } else {
-                            response_with_code(StatusCode::NOT_FOUND)
-                        }
-                    },
-                    (&Method::DELETE, Some(id)) => {
-                        if users.contains(id) {
-                            users.remove(id);
-                            response_with_code(StatusCode::OK)
-                        } else {
-                            response_with_code(StatusCode::NOT_FOUND)
-                        }
-                    },
-                    _ => {
-                        response_with_code(StatusCode::METHOD_NOT_ALLOWED)
-                    }
+                Matched::NotFound => {
+                    Box::pin(async { Ok(response_with_code(StatusCode::NOT_FOUND)) })
                 }
-            } else {
-                response_with_code(StatusCode::NOT_FOUND)
             }
         };
 
-        future::ok(response)
+        let request_timeout = self.request_timeout;
+
+        Box::pin(async move {
+            let response = match tokio::time::timeout(request_timeout, fut).await {
+                Ok(result) => result?,
+                Err(_) => return Ok(response_with_code(StatusCode::SERVICE_UNAVAILABLE)),
+            };
+
+            compress_response(accept_encoding.as_ref(), response).await
+        })
     }
 }
 
 impl<T> Service<T> for MakeMicroService {
     type Response = MicroService;
     type Error = std::io::Error;
-    type Future = future::Ready<Result<Self::Response, Self::Error>>;
+    type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         Ok(()).into()
     }
 
     fn call(&mut self, _: T) -> Self::Future {
-        future::ok(MicroService::new(self.user_db.clone()))
+        std::future::ready(Ok(MicroService::new(
+            self.router.clone(),
+            self.relay.clone(),
+            self.user_db.clone(),
+            self.templates.clone(),
+            self.request_timeout,
+        )))
     }
 }
 
+fn build_router() -> Router {
+    let mut router = Router::new();
+
+    router.add(
+        Regex::new("^/(index\\.html?)?$").unwrap(),
+        Method::GET,
+        handle_index,
+    );
+    router.add(
+        Regex::new("^/users/?$").unwrap(),
+        Method::GET,
+        handle_users_list,
+    );
+    router.add(
+        Regex::new("^/user/?$").unwrap(),
+        Method::POST,
+        handle_user_create,
+    );
+    router.add(
+        Regex::new("^/user/(?P<user_id>\\d+)/?$").unwrap(),
+        Method::POST,
+        handle_user_create_with_id,
+    );
+    router.add(
+        Regex::new("^/user/(?P<user_id>\\d+)/?$").unwrap(),
+        Method::GET,
+        handle_user_get,
+    );
+    router.add(
+        Regex::new("^/user/(?P<user_id>\\d+)/?$").unwrap(),
+        Method::PUT,
+        handle_user_update,
+    );
+    router.add(
+        Regex::new("^/user/(?P<user_id>\\d+)/?$").unwrap(),
+        Method::DELETE,
+        handle_user_delete,
+    );
+
+    router
+}
+
+/// Path prefixes proxied to backend services, read from `RELAY_ROUTES`: a
+/// comma-separated list of `prefix=host:port` pairs, e.g.
+/// `RELAY_ROUTES=/api/orders=orders.internal:9000,/api/billing=billing.internal:9001`.
+/// Unset or empty means no prefixes are proxied. Malformed entries are
+/// logged and skipped rather than failing startup.
+fn build_relay_config() -> RelayConfig {
+    let mut config = RelayConfig::new();
+
+    let routes = match std::env::var("RELAY_ROUTES") {
+        Ok(routes) => routes,
+        Err(_) => return config,
+    };
+
+    for entry in routes.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        match entry.split_once('=') {
+            Some((prefix, upstream)) => match upstream.parse() {
+                Ok(authority) => config.add(prefix, authority),
+                Err(e) => eprintln!("RELAY_ROUTES: invalid upstream {:?}: {}", upstream, e),
+            },
+            None => eprintln!("RELAY_ROUTES: invalid entry {:?}, expected PREFIX=HOST:PORT", entry),
+        }
+    }
+
+    config
+}
+
+fn handle_index(
+    _req: Request<Body>,
+    _captures: Captures,
+    user_db: UserDb,
+    templates: Templates,
+) -> HandlerFuture {
+    Box::pin(async move {
+        let data = templates::IndexData {
+            version: env!("CARGO_PKG_VERSION"),
+            user_count: user_db.users.len(),
+        };
+        let html = templates
+            .render(templates::INDEX_TEMPLATE, &data)
+            .expect("index template renders");
+
+        Ok(html_response(html))
+    })
+}
+
+fn handle_users_list(
+    req: Request<Body>,
+    _captures: Captures,
+    user_db: UserDb,
+    templates: Templates,
+) -> HandlerFuture {
+    Box::pin(async move {
+        if accepts_html(&req) {
+            let users = user_db
+                .users
+                .iter()
+                .map(|entry| templates::UserRow {
+                    id: *entry.key(),
+                    name: entry.value().name.clone(),
+                    email: entry.value().email.clone(),
+                })
+                .collect();
+            let data = templates::UsersData { users };
+            let html = templates
+                .render(templates::USERS_TEMPLATE, &data)
+                .expect("users template renders");
+
+            Ok(html_response(html))
+        } else {
+            let list = user_db
+                .users
+                .iter()
+                .map(|entry| entry.key().to_string())
+                .collect::<Vec<String>>()
+                .join(",");
+
+            Ok(Response::new(Body::from(list)))
+        }
+    })
+}
+
+fn accepts_html(req: &Request<Body>) -> bool {
+    req.headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.contains("text/html"))
+        .unwrap_or(false)
+}
+
+fn handle_user_create(
+    req: Request<Body>,
+    _captures: Captures,
+    user_db: UserDb,
+    _templates: Templates,
+) -> HandlerFuture {
+    Box::pin(async move {
+        let bytes = body::to_bytes(req.into_body()).await?;
+        let response = match serde_json::from_slice::<UserData>(&bytes) {
+            Ok(data) => {
+                let id = user_db.insert(data);
+                Response::new(id.to_string().into())
+            }
+            Err(e) => bad_request(format!("invalid user JSON: {}", e)),
+        };
+
+        Ok(response)
+    })
+}
+
+/// Creation assigns the user's ID; posting to an ID the caller picked is a
+/// malformed request rather than merely an unsupported method on that path.
+fn handle_user_create_with_id(
+    _req: Request<Body>,
+    _captures: Captures,
+    _user_db: UserDb,
+    _templates: Templates,
+) -> HandlerFuture {
+    Box::pin(async move {
+        Ok(bad_request(
+            "POST /user/{id} is not supported; POST /user to create a user",
+        ))
+    })
+}
+
+fn handle_user_get(
+    _req: Request<Body>,
+    captures: Captures,
+    user_db: UserDb,
+    _templates: Templates,
+) -> HandlerFuture {
+    Box::pin(async move {
+        let response = match parse_user_id(&captures) {
+            Some(id) => match user_db.users.get(&id) {
+                Some(data) => json_response(StatusCode::OK, data.value()),
+                None => response_with_code(StatusCode::NOT_FOUND),
+            },
+            None => response_with_code(StatusCode::NOT_FOUND),
+        };
+
+        Ok(response)
+    })
+}
+
+fn handle_user_update(
+    req: Request<Body>,
+    captures: Captures,
+    user_db: UserDb,
+    _templates: Templates,
+) -> HandlerFuture {
+    Box::pin(async move {
+        let id = match parse_user_id(&captures) {
+            Some(id) => id,
+            None => return Ok(response_with_code(StatusCode::NOT_FOUND)),
+        };
+
+        let bytes = body::to_bytes(req.into_body()).await?;
+        let response = match serde_json::from_slice::<UserData>(&bytes) {
+            Ok(data) => {
+                if let Some(mut existing) = user_db.users.get_mut(&id) {
+                    *existing.value_mut() = data;
+                    response_with_code(StatusCode::OK)
+                } else {
+                    response_with_code(StatusCode::NOT_FOUND)
+                }
+            }
+            Err(e) => bad_request(format!("invalid user JSON: {}", e)),
+        };
+
+        Ok(response)
+    })
+}
+
+fn handle_user_delete(
+    _req: Request<Body>,
+    captures: Captures,
+    user_db: UserDb,
+    _templates: Templates,
+) -> HandlerFuture {
+    Box::pin(async move {
+        let response = match parse_user_id(&captures) {
+            Some(id) => {
+                if user_db.users.remove(&id).is_some() {
+                    response_with_code(StatusCode::OK)
+                } else {
+                    response_with_code(StatusCode::NOT_FOUND)
+                }
+            }
+            None => response_with_code(StatusCode::NOT_FOUND),
+        };
+
+        Ok(response)
+    })
+}
+
+fn parse_user_id(captures: &Captures) -> Option<UserId> {
+    captures.get("user_id")?.parse::<UserId>().ok()
+}
+
 #[tokio::main]
 async fn main() {
-    let user_db: UserDb = Arc::new(Mutex::new(Slab::new()));
+    let config = Config::from_env();
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
-    let server = Server::bind(&addr).serve(MakeMicroService::new(user_db));
+    let router = Arc::new(build_router());
+    let relay = Relay::new(Arc::new(build_relay_config()));
+    let user_db: UserDb = Arc::new(UserStore::default());
+    let templates = templates::build_registry();
+
+    let make_service =
+        MakeMicroService::new(router, relay, user_db, templates, config.request_timeout);
+    let server = Server::bind(&config.bind_addr)
+        .serve(make_service)
+        .with_graceful_shutdown(shutdown_signal());
 
     if let Err(e) = server.await {
         eprintln!("server error: {}", e);
     }
 }
 
+async fn shutdown_signal() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        eprintln!("failed to listen for ctrl-c: {}", e);
+    }
+}
+
 fn response_with_code(status_code: StatusCode) -> Response<Body> {
     Response::builder()
         .status(status_code)
         .body(Body::empty())
         .unwrap()
-}
\ No newline at end of file
+}
+
+fn json_response<T: Serialize>(status_code: StatusCode, data: &T) -> Response<Body> {
+    let body = serde_json::to_vec(data).unwrap();
+    Response::builder()
+        .status(status_code)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn html_response(html: String) -> Response<Body> {
+    Response::builder()
+        .header(CONTENT_TYPE, "text/html")
+        .body(Body::from(html))
+        .unwrap()
+}
+
+fn bad_request(message: impl Into<String>) -> Response<Body> {
+    json_response(
+        StatusCode::BAD_REQUEST,
+        &ErrorBody {
+            error: message.into(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn a_handler_that_outlives_the_request_timeout_yields_503() {
+        let mut router = Router::new();
+        router.add(
+            Regex::new("^/slow$").unwrap(),
+            Method::GET,
+            |_req, _captures, _user_db, _templates| {
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(response_with_code(StatusCode::OK))
+                })
+            },
+        );
+        let relay = Relay::new(Arc::new(RelayConfig::new()));
+        let user_db: UserDb = Arc::new(UserStore::default());
+        let templates = templates::build_registry();
+
+        let mut service = MicroService::new(
+            Arc::new(router),
+            relay,
+            user_db,
+            templates,
+            Duration::from_millis(5),
+        );
+
+        let req = Request::builder().uri("/slow").body(Body::empty()).unwrap();
+        let response = service.call(req).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn posting_to_a_specific_user_id_is_a_bad_request() {
+        let router = build_router();
+        let user_db: UserDb = Arc::new(UserStore::default());
+        let templates = templates::build_registry();
+
+        let (handler, captures) = match router.find("/user/5", &Method::POST) {
+            Matched::Found { handler, captures } => (handler, captures),
+            _ => panic!("expected POST /user/<id> to match a route"),
+        };
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/user/5")
+            .body(Body::empty())
+            .unwrap();
+        let response = handler(req, captures, user_db, templates).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn creating_a_user_makes_it_retrievable_by_id() {
+        let user_db: UserDb = Arc::new(UserStore::default());
+        let templates = templates::build_registry();
+
+        let create_req = Request::builder()
+            .method(Method::POST)
+            .uri("/user")
+            .body(Body::from(r#"{"name":"Ada","email":"ada@example.com"}"#))
+            .unwrap();
+        let create_response = handle_user_create(
+            create_req,
+            Captures::new(),
+            user_db.clone(),
+            templates.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(create_response.status(), StatusCode::OK);
+
+        let id = body::to_bytes(create_response.into_body())
+            .await
+            .unwrap();
+        let id = std::str::from_utf8(&id).unwrap().to_string();
+
+        let mut captures = Captures::new();
+        captures.insert("user_id".to_string(), id);
+        let get_req = Request::builder()
+            .method(Method::GET)
+            .uri("/user/0")
+            .body(Body::empty())
+            .unwrap();
+        let get_response = handle_user_get(get_req, captures, user_db, templates)
+            .await
+            .unwrap();
+
+        assert_eq!(get_response.status(), StatusCode::OK);
+        let body = body::to_bytes(get_response.into_body()).await.unwrap();
+        let data: UserData = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data.name, "Ada");
+        assert_eq!(data.email, "ada@example.com");
+    }
+
+    #[tokio::test]
+    async fn updating_a_user_replaces_its_stored_data() {
+        let user_db: UserDb = Arc::new(UserStore::default());
+        let templates = templates::build_registry();
+        let id = user_db.insert(UserData {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+        });
+
+        let mut captures = Captures::new();
+        captures.insert("user_id".to_string(), id.to_string());
+        let update_req = Request::builder()
+            .method(Method::PUT)
+            .uri(format!("/user/{}", id))
+            .body(Body::from(r#"{"name":"Grace","email":"grace@example.com"}"#))
+            .unwrap();
+        let update_response =
+            handle_user_update(update_req, captures, user_db.clone(), templates)
+                .await
+                .unwrap();
+
+        assert_eq!(update_response.status(), StatusCode::OK);
+        assert_eq!(user_db.users.get(&id).unwrap().name, "Grace");
+    }
+
+    #[tokio::test]
+    async fn creating_a_user_with_malformed_json_is_a_bad_request() {
+        let user_db: UserDb = Arc::new(UserStore::default());
+        let templates = templates::build_registry();
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/user")
+            .body(Body::from("not json"))
+            .unwrap();
+        let response = handle_user_create(req, Captures::new(), user_db, templates)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn updating_a_user_with_malformed_json_is_a_bad_request() {
+        let user_db: UserDb = Arc::new(UserStore::default());
+        let templates = templates::build_registry();
+        let id = user_db.insert(UserData {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+        });
+
+        let mut captures = Captures::new();
+        captures.insert("user_id".to_string(), id.to_string());
+        let req = Request::builder()
+            .method(Method::PUT)
+            .uri(format!("/user/{}", id))
+            .body(Body::from("not json"))
+            .unwrap();
+        let response = handle_user_update(req, captures, user_db, templates)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn users_list_without_accept_header_is_a_plain_comma_list() {
+        let user_db: UserDb = Arc::new(UserStore::default());
+        user_db.insert(UserData {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+        });
+        let templates = templates::build_registry();
+
+        let req = Request::builder()
+            .uri("/users")
+            .body(Body::empty())
+            .unwrap();
+        let response = handle_users_list(req, Captures::new(), user_db, templates)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE),
+            None,
+            "plain listing shouldn't claim a content type"
+        );
+        let body = body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "0".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn users_list_with_accept_html_renders_a_table() {
+        let user_db: UserDb = Arc::new(UserStore::default());
+        user_db.insert(UserData {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+        });
+        let templates = templates::build_registry();
+
+        let req = Request::builder()
+            .uri("/users")
+            .header(ACCEPT, "text/html")
+            .body(Body::empty())
+            .unwrap();
+        let response = handle_users_list(req, Captures::new(), user_db, templates)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE),
+            Some(&hyper::header::HeaderValue::from_static("text/html"))
+        );
+        let body = body::to_bytes(response.into_body()).await.unwrap();
+        let html = std::str::from_utf8(&body).unwrap();
+        assert!(html.contains("Ada"));
+        assert!(html.contains("ada@example.com"));
+    }
+
+    #[tokio::test]
+    async fn index_reflects_the_store_s_user_count() {
+        let user_db: UserDb = Arc::new(UserStore::default());
+        user_db.insert(UserData {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+        });
+        let templates = templates::build_registry();
+
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = handle_index(req, Captures::new(), user_db, templates)
+            .await
+            .unwrap();
+
+        let body = body::to_bytes(response.into_body()).await.unwrap();
+        let html = std::str::from_utf8(&body).unwrap();
+        assert!(html.contains("1 user(s) registered"));
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use hyper::client::HttpConnector;
+use hyper::header::HOST;
+use hyper::http::uri::{Authority, Scheme, Uri};
+use hyper::{Body, Client, Request, Response, StatusCode};
+
+use crate::response_with_code;
+
+/// Maps a path prefix (e.g. `/api/orders`) to the authority (host:port) of
+/// the upstream service that should handle requests under that prefix.
+#[derive(Debug, Clone)]
+pub struct ProxyRoute {
+    pub prefix: String,
+    pub upstream: Authority,
+}
+
+/// The set of path prefixes this instance proxies to backend services.
+#[derive(Debug, Default)]
+pub struct RelayConfig {
+    routes: Vec<ProxyRoute>,
+}
+
+impl RelayConfig {
+    pub fn new() -> Self {
+        RelayConfig { routes: Vec::new() }
+    }
+
+    pub fn add(&mut self, prefix: impl Into<String>, upstream: Authority) {
+        self.routes.push(ProxyRoute {
+            prefix: prefix.into(),
+            upstream,
+        });
+    }
+
+    fn match_prefix(&self, path: &str) -> Option<&Authority> {
+        self.routes
+            .iter()
+            .find(|route| prefix_matches(path, &route.prefix))
+            .map(|route| &route.upstream)
+    }
+}
+
+/// True if `path` falls under `prefix`, i.e. `path` equals `prefix` or
+/// continues with a `/`. A plain `starts_with` would also match
+/// `/api/orders-v2` against a `/api/orders` route, proxying requests that
+/// were never meant to reach that backend.
+fn prefix_matches(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .map(|rest| rest.is_empty() || rest.starts_with('/'))
+        .unwrap_or(false)
+}
+
+/// Forwards matched requests to upstream backends and relays their
+/// responses back to the caller, turning the service into a small gateway.
+#[derive(Debug, Clone)]
+pub struct Relay {
+    config: Arc<RelayConfig>,
+    client: Client<HttpConnector>,
+}
+
+impl Relay {
+    pub fn new(config: Arc<RelayConfig>) -> Self {
+        Relay {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    /// Returns the upstream authority a path should be forwarded to, if any.
+    pub fn find(&self, path: &str) -> Option<Authority> {
+        self.config.match_prefix(path).cloned()
+    }
+
+    /// Rebuilds `req`'s URI against `upstream` and relays it, returning
+    /// `502 Bad Gateway` if the upstream can't be reached or the request
+    /// can't be re-targeted.
+    pub async fn forward(&self, upstream: Authority, mut req: Request<Body>) -> Response<Body> {
+        let mut parts = req.uri().clone().into_parts();
+        parts.scheme = Some(Scheme::HTTP);
+        parts.authority = Some(upstream.clone());
+
+        let uri = match Uri::from_parts(parts) {
+            Ok(uri) => uri,
+            Err(_) => return response_with_code(StatusCode::BAD_GATEWAY),
+        };
+        *req.uri_mut() = uri;
+
+        // hyper only derives the `Host` header from the URI when none is
+        // present, so the inbound request's original Host must be replaced
+        // or upstream name-based routing/SNI will see the wrong one.
+        match upstream.to_string().parse() {
+            Ok(host) => {
+                req.headers_mut().insert(HOST, host);
+            }
+            Err(_) => return response_with_code(StatusCode::BAD_GATEWAY),
+        }
+
+        match self.client.request(req).await {
+            Ok(response) => response,
+            Err(_) => response_with_code(StatusCode::BAD_GATEWAY),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use hyper::server::conn::Http;
+    use hyper::service::service_fn;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn authority(addr: SocketAddr) -> Authority {
+        addr.to_string().parse().unwrap()
+    }
+
+    #[test]
+    fn find_matches_a_route_and_everything_under_it() {
+        let mut config = RelayConfig::new();
+        config.add("/api/orders", "orders.internal:9000".parse().unwrap());
+        let relay = Relay::new(Arc::new(config));
+
+        assert!(relay.find("/api/orders").is_some());
+        assert!(relay.find("/api/orders/123").is_some());
+    }
+
+    #[test]
+    fn find_does_not_match_a_path_that_merely_shares_the_prefix() {
+        let mut config = RelayConfig::new();
+        config.add("/api/orders", "orders.internal:9000".parse().unwrap());
+        let relay = Relay::new(Arc::new(config));
+
+        assert!(relay.find("/api/orders-v2").is_none());
+        assert!(relay.find("/api/ordersarchive").is_none());
+    }
+
+    #[test]
+    fn find_returns_none_when_no_route_registered() {
+        let relay = Relay::new(Arc::new(RelayConfig::new()));
+
+        assert!(relay.find("/anything").is_none());
+    }
+
+    /// Spawns a one-shot server that echoes back the `Host` header and path
+    /// it received, so `forward`'s URI/Host rewriting can be observed.
+    async fn spawn_echo_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let service = service_fn(|req: Request<Body>| async move {
+                let host = req
+                    .headers()
+                    .get(HOST)
+                    .and_then(|h| h.to_str().ok())
+                    .unwrap_or("")
+                    .to_string();
+                let path = req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("");
+                Ok::<_, hyper::Error>(Response::new(Body::from(format!("{}|{}", host, path))))
+            });
+            Http::new().serve_connection(stream, service).await.unwrap();
+        });
+
+        addr
+    }
+
+    #[tokio::test]
+    async fn forward_rewrites_uri_and_host_to_the_upstream_authority() {
+        let addr = spawn_echo_server().await;
+        let upstream = authority(addr);
+        let relay = Relay::new(Arc::new(RelayConfig::new()));
+
+        let req = Request::builder()
+            .uri("/api/orders/123?sort=asc")
+            .header(HOST, "original.example")
+            .body(Body::empty())
+            .unwrap();
+        let response = relay.forward(upstream.clone(), req).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            body,
+            format!("{}|/api/orders/123?sort=asc", upstream).as_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn forward_returns_bad_gateway_when_upstream_is_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = authority(listener.local_addr().unwrap());
+        drop(listener);
+
+        let relay = Relay::new(Arc::new(RelayConfig::new()));
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = relay.forward(upstream, req).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use hyper::{Body, Method, Request, Response};
+use regex::Regex;
+
+use crate::templates::Templates;
+use crate::UserDb;
+
+/// Named capture groups pulled out of a matched route pattern, owned so they
+/// can be moved into a handler's future without borrowing from the request path.
+pub type Captures = HashMap<String, String>;
+
+pub type HandlerFuture = Pin<Box<dyn Future<Output = Result<Response<Body>, hyper::Error>> + Send>>;
+
+type Handler =
+    Arc<dyn Fn(Request<Body>, Captures, UserDb, Templates) -> HandlerFuture + Send + Sync>;
+
+struct Route {
+    pattern: Regex,
+    method: Method,
+    handler: Handler,
+}
+
+/// Result of looking up a path/method pair in the `Router`.
+pub enum Matched {
+    Found {
+        handler: Handler,
+        captures: Captures,
+    },
+    MethodNotAllowed,
+    NotFound,
+}
+
+/// A declarative alternative to an if/else dispatch chain: routes are
+/// registered once at startup and matched in order against each request.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl std::fmt::Debug for Router {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Router")
+            .field("routes", &self.routes.len())
+            .finish()
+    }
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router { routes: Vec::new() }
+    }
+
+    pub fn add<F>(&mut self, pattern: Regex, method: Method, handler: F)
+    where
+        F: Fn(Request<Body>, Captures, UserDb, Templates) -> HandlerFuture + Send + Sync + 'static,
+    {
+        self.routes.push(Route {
+            pattern,
+            method,
+            handler: Arc::new(handler),
+        });
+    }
+
+    /// Walks the routes in registration order. A path that matches some
+    /// route's pattern but not with the requested method yields
+    /// `MethodNotAllowed` rather than `NotFound`, so that semantics is
+    /// preserved without every handler having to re-check it.
+    pub fn find(&self, path: &str, method: &Method) -> Matched {
+        let mut path_matched = false;
+
+        for route in &self.routes {
+            if let Some(caps) = route.pattern.captures(path) {
+                path_matched = true;
+
+                if route.method == *method {
+                    let captures = route
+                        .pattern
+                        .capture_names()
+                        .flatten()
+                        .filter_map(|name| {
+                            caps.name(name)
+                                .map(|m| (name.to_string(), m.as_str().to_string()))
+                        })
+                        .collect();
+
+                    return Matched::Found {
+                        handler: route.handler.clone(),
+                        captures,
+                    };
+                }
+            }
+        }
+
+        if path_matched {
+            Matched::MethodNotAllowed
+        } else {
+            Matched::NotFound
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noop_handler(
+        _req: Request<Body>,
+        _captures: Captures,
+        _user_db: UserDb,
+        _templates: Templates,
+    ) -> HandlerFuture {
+        Box::pin(async { Ok(Response::new(Body::empty())) })
+    }
+
+    #[test]
+    fn matches_registered_path_and_method() {
+        let mut router = Router::new();
+        router.add(
+            Regex::new("^/widgets/?$").unwrap(),
+            Method::GET,
+            noop_handler,
+        );
+
+        assert!(matches!(
+            router.find("/widgets", &Method::GET),
+            Matched::Found { .. }
+        ));
+    }
+
+    #[test]
+    fn method_mismatch_on_known_path_is_method_not_allowed() {
+        let mut router = Router::new();
+        router.add(
+            Regex::new("^/widgets/?$").unwrap(),
+            Method::GET,
+            noop_handler,
+        );
+
+        assert!(matches!(
+            router.find("/widgets", &Method::POST),
+            Matched::MethodNotAllowed
+        ));
+    }
+
+    #[test]
+    fn unmatched_path_is_not_found() {
+        let mut router = Router::new();
+        router.add(
+            Regex::new("^/widgets/?$").unwrap(),
+            Method::GET,
+            noop_handler,
+        );
+
+        assert!(matches!(
+            router.find("/nope", &Method::GET),
+            Matched::NotFound
+        ));
+    }
+
+    #[test]
+    fn empty_router_is_not_found() {
+        let router = Router::new();
+
+        assert!(matches!(
+            router.find("/anything", &Method::GET),
+            Matched::NotFound
+        ));
+    }
+
+    #[tokio::test]
+    async fn first_registered_route_wins_when_two_share_path_and_method() {
+        let mut router = Router::new();
+        router.add(
+            Regex::new("^/widgets/?$").unwrap(),
+            Method::GET,
+            |_req, _captures, _user_db, _templates| {
+                Box::pin(async { Ok(crate::response_with_code(hyper::StatusCode::OK)) })
+            },
+        );
+        router.add(
+            Regex::new("^/widgets/?$").unwrap(),
+            Method::GET,
+            |_req, _captures, _user_db, _templates| {
+                Box::pin(async { Ok(crate::response_with_code(hyper::StatusCode::IM_A_TEAPOT)) })
+            },
+        );
+
+        let (handler, captures) = match router.find("/widgets", &Method::GET) {
+            Matched::Found { handler, captures } => (handler, captures),
+            _ => panic!("expected the route to match"),
+        };
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/widgets")
+            .body(Body::empty())
+            .unwrap();
+        let user_db: UserDb = Arc::new(crate::UserStore::default());
+        let templates = crate::templates::build_registry();
+        let response = handler(req, captures, user_db, templates).await.unwrap();
+
+        assert_eq!(response.status(), hyper::StatusCode::OK);
+    }
+
+    #[test]
+    fn captures_named_groups_from_the_matched_route() {
+        let mut router = Router::new();
+        router.add(
+            Regex::new("^/user/(?P<user_id>\\d+)/?$").unwrap(),
+            Method::GET,
+            noop_handler,
+        );
+
+        match router.find("/user/42", &Method::GET) {
+            Matched::Found { captures, .. } => {
+                assert_eq!(captures.get("user_id"), Some(&"42".to_string()));
+            }
+            _ => panic!("expected the route to match"),
+        }
+    }
+}
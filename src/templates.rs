@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use handlebars::Handlebars;
+use serde::Serialize;
+
+pub type Templates = Arc<Handlebars<'static>>;
+
+pub const INDEX_TEMPLATE: &str = "index";
+pub const USERS_TEMPLATE: &str = "users";
+
+const INDEX_SOURCE: &str = r#"
+<!doctype html>
+<html>
+    <head>
+        <title>Rust Microservice</title>
+    </head>
+    <body>
+        <h3>Rust Microservice</h3>
+        <p>version {{version}}, {{user_count}} user(s) registered</p>
+    </body>
+</html>
+"#;
+
+const USERS_SOURCE: &str = r#"
+<!doctype html>
+<html>
+    <head>
+        <title>Users</title>
+    </head>
+    <body>
+        <h3>Users</h3>
+        <table>
+            <thead>
+                <tr><th>ID</th><th>Name</th><th>Email</th></tr>
+            </thead>
+            <tbody>
+                {{#each users}}
+                <tr><td>{{this.id}}</td><td>{{this.name}}</td><td>{{this.email}}</td></tr>
+                {{/each}}
+            </tbody>
+        </table>
+    </body>
+</html>
+"#;
+
+/// Registers the service's templates once at startup.
+pub fn build_registry() -> Templates {
+    let mut handlebars = Handlebars::new();
+    handlebars
+        .register_template_string(INDEX_TEMPLATE, INDEX_SOURCE)
+        .expect("index template is valid handlebars");
+    handlebars
+        .register_template_string(USERS_TEMPLATE, USERS_SOURCE)
+        .expect("users template is valid handlebars");
+
+    Arc::new(handlebars)
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexData {
+    pub version: &'static str,
+    pub user_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserRow {
+    pub id: u64,
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsersData {
+    pub users: Vec<UserRow>,
+}